@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::media::MediaDetails;
+
+const CONFIG_FILE_NAME: &str = "video-convert.toml";
+
+/// Mirrors pict-rs's transcode-decision idea: thresholds the GIF path
+/// checks itself against before converting, so one oversized drop doesn't
+/// quietly turn into a multi-hundred-megabyte GIF.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MediaLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_area: u32,
+    pub max_frame_count: u32,
+    pub max_fps: u32,
+}
+
+impl Default for MediaLimits {
+    fn default() -> Self {
+        MediaLimits {
+            max_width: 1920,
+            max_height: 1080,
+            max_area: 1920 * 1080,
+            max_frame_count: 600,
+            max_fps: 30,
+        }
+    }
+}
+
+/// What to actually pass to ffmpeg for a GIF conversion, after checking the
+/// requested fps/width against a source's dimensions and duration.
+#[derive(Debug, Clone)]
+pub struct GifPlan {
+    pub fps: u32,
+    pub width: u32,
+    /// Set when the requested settings had to be cut down to fit the
+    /// limits, so the caller can surface it instead of silently truncating.
+    pub warning: Option<String>,
+}
+
+impl MediaLimits {
+    /// Clamps `requested_fps`/`requested_width` to these limits for
+    /// `media`, downscaling width (ffmpeg's `scale=width:-1` preserves
+    /// aspect ratio) and capping fps rather than rejecting the source.
+    pub fn plan_gif(&self, media: &MediaDetails, requested_fps: u32, requested_width: u32) -> GifPlan {
+        let mut fps = requested_fps.clamp(1, self.max_fps);
+        let mut width = requested_width.clamp(1, self.max_width);
+        let mut notes = Vec::new();
+
+        if fps < requested_fps {
+            notes.push(format!("帧率超出上限，已从 {requested_fps} 降至 {fps}"));
+        }
+        if width < requested_width {
+            notes.push(format!("宽度超出上限，已从 {requested_width} 降至 {width}"));
+        }
+
+        if media.width > 0 {
+            let height = (width as f64 * media.height as f64 / media.width as f64).round();
+            if height > self.max_height as f64 {
+                let clamped_width = (width as f64 * self.max_height as f64 / height).floor() as u32;
+                notes.push(format!("高度超出上限，宽度进一步降至 {clamped_width}"));
+                width = clamped_width.max(1);
+            }
+
+            let area = width as f64 * (width as f64 * media.height as f64 / media.width as f64);
+            if area > self.max_area as f64 {
+                let clamped_width = (width as f64 * (self.max_area as f64 / area).sqrt()).floor() as u32;
+                notes.push(format!("面积超出上限，宽度进一步降至 {clamped_width}"));
+                width = clamped_width.max(1);
+            }
+        }
+
+        if media.duration_secs > 0.0 {
+            let estimated_frames = (media.duration_secs * fps as f64).round() as u32;
+            if estimated_frames > self.max_frame_count {
+                let clamped_fps = ((self.max_frame_count as f64 / media.duration_secs).floor() as u32).max(1);
+                notes.push(format!(
+                    "预计帧数 {estimated_frames} 超出上限 {}，帧率进一步降至 {clamped_fps}",
+                    self.max_frame_count
+                ));
+                fps = clamped_fps.min(fps);
+            }
+        }
+
+        GifPlan {
+            fps,
+            width,
+            warning: if notes.is_empty() {
+                None
+            } else {
+                Some(notes.join("; "))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    gif_limits: MediaLimits,
+}
+
+fn read_media_limits() -> MediaLimits {
+    let path = Path::new(CONFIG_FILE_NAME);
+    if !path.is_file() {
+        return MediaLimits::default();
+    }
+
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(_) => return MediaLimits::default(),
+    };
+
+    match toml::from_str::<ConfigFile>(&raw) {
+        Ok(config) => config.gif_limits,
+        Err(e) => {
+            eprintln!("{CONFIG_FILE_NAME} is not valid TOML ({e}), using default media limits.");
+            MediaLimits::default()
+        }
+    }
+}
+
+static MEDIA_LIMITS: OnceLock<MediaLimits> = OnceLock::new();
+
+/// Reads `./video-convert.toml` (falling back to the built-in defaults) the
+/// first time it's called — `main` forces that to happen at startup — and
+/// reuses the parsed limits for the rest of the process, so a GIF
+/// conversion isn't re-reading and re-parsing the same file from disk.
+pub fn load_media_limits() -> MediaLimits {
+    MEDIA_LIMITS.get_or_init(read_media_limits).clone()
+}
+
+#[test]
+fn test_plan_gif_within_limits_is_unclamped() {
+    let media = MediaDetails {
+        width: 1920,
+        height: 1080,
+        duration_secs: 10.0,
+        ..Default::default()
+    };
+    let plan = MediaLimits::default().plan_gif(&media, 15, 480);
+    assert_eq!(plan.fps, 15);
+    assert_eq!(plan.width, 480);
+    assert!(plan.warning.is_none());
+}
+
+#[test]
+fn test_plan_gif_clamps_fps_to_max() {
+    let plan = MediaLimits::default().plan_gif(&MediaDetails::default(), 60, 480);
+    assert_eq!(plan.fps, 30);
+    assert!(plan.warning.is_some());
+}
+
+#[test]
+fn test_plan_gif_clamps_width_to_max() {
+    let plan = MediaLimits::default().plan_gif(&MediaDetails::default(), 15, 3000);
+    assert_eq!(plan.width, 1920);
+    assert!(plan.warning.is_some());
+}
+
+#[test]
+fn test_plan_gif_clamps_fps_for_frame_count_budget() {
+    let media = MediaDetails {
+        duration_secs: 100.0,
+        ..Default::default()
+    };
+    // 30fps * 100s = 3000 frames, well past the 600 frame default budget.
+    let plan = MediaLimits::default().plan_gif(&media, 30, 480);
+    assert_eq!(plan.fps, 6);
+    assert!(plan.warning.is_some());
+}
+
+#[test]
+fn test_plan_gif_shrinks_width_to_respect_aspect_height_cap() {
+    // A 480-wide output on a portrait 1:2 source would be 960 tall, past
+    // the 1080 default height cap only once width grows further, so pick a
+    // source where the derived height actually exceeds the limit.
+    let media = MediaDetails {
+        width: 100,
+        height: 300,
+        duration_secs: 1.0,
+        ..Default::default()
+    };
+    let mut limits = MediaLimits::default();
+    limits.max_height = 200;
+    let plan = limits.plan_gif(&media, 15, 100);
+    // height = 100 * 300 / 100 = 300 > 200, so width is cut down.
+    assert!(plan.width < 100);
+    assert!(plan.warning.is_some());
+}
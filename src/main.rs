@@ -3,15 +3,31 @@ use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
+use std::process::Stdio;
+
+mod config;
+mod media;
+mod queue;
+mod transcode;
+
+use config::load_media_limits;
+use media::{ffprobe_media, probe_media_sync, MediaDetails};
+use queue::{ItemStatus, QueueItem};
+use transcode::{
+    build_transition_filter, transition_total_secs, validate, AudioCodec, Container, SubtitleMode,
+    TranscodeOptions, VideoCodec,
+};
 
 use iced::alignment;
 use iced::executor;
 use iced::subscription;
-use iced::widget::{button, container, text, Column};
+use iced::widget::{button, container, pick_list, progress_bar, text, text_input, Column, Row};
 use iced::window;
 use iced::window::Event as WindowEvent;
 use iced::Event;
 use iced::{Alignment, Application, Command, Element, Length, Settings, Subscription, Theme};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command as TokioCommand};
 
 const FONT: &[u8] = include_bytes!(concat!(
     env!("CARGO_MANIFEST_DIR"),
@@ -19,6 +35,10 @@ const FONT: &[u8] = include_bytes!(concat!(
 ));
 
 pub fn main() -> iced::Result {
+    // Warm the media-limits cache now so every conversion reuses this one
+    // parse instead of re-reading video-convert.toml from disk each time.
+    load_media_limits();
+
     VideoProcessor::run(Settings {
         default_font: Some(FONT),
         window: window::Settings {
@@ -29,22 +49,51 @@ pub fn main() -> iced::Result {
     })
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 struct SelectTargetCtx {
-    video: PathBuf,
+    items: Vec<QueueItem>,
+    media: MediaDetails,
+    options: TranscodeOptions,
+    validation_error: Option<String>,
+}
+
+impl Default for SelectTargetCtx {
+    fn default() -> Self {
+        SelectTargetCtx {
+            items: Vec::new(),
+            media: MediaDetails::default(),
+            options: TranscodeOptions::default(),
+            validation_error: None,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 struct CompleteCtx {
-    target_path: PathBuf,
+    items: Vec<QueueItem>,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct GeneratingCtx {
+    items: Vec<QueueItem>,
+    current_index: usize,
+    options: TranscodeOptions,
+    progress: f32,
+    /// GIF fps/width clamp notices (`GifPlan::warning`) collected as the
+    /// batch runs, surfaced in `gen_file_view`/`complete_view` instead of
+    /// only going to stdout.
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Default)]
 enum VideoProcessor {
     #[default]
     SelectFile,
+    Probing(Vec<QueueItem>),
     SelectTarget(SelectTargetCtx),
-    GeneratingFile,
+    SelectTransition(SelectTargetCtx),
+    GeneratingFile(GeneratingCtx),
     Complete(CompleteCtx),
     Error,
 }
@@ -52,8 +101,25 @@ enum VideoProcessor {
 #[derive(Debug, Clone)]
 enum Message {
     EventOccurred(Event),
-    Submit(String),
+    Probed(MediaDetails),
+    Submit,
+    ContainerChanged(Container),
+    VideoCodecChanged(VideoCodec),
+    AudioCodecChanged(AudioCodec),
+    QualityChanged(String),
+    GifFpsChanged(String),
+    GifWidthChanged(String),
+    SubtitleModeChanged(SubtitleMode),
+    ClearSubtitle,
+    OpenTransitionEditor,
+    CloseTransitionEditor,
+    TransitionSecsChanged(String),
+    ClearIntro,
+    ClearOutro,
+    Progress(f32),
+    GifLimitsWarning(String),
     FfmpegComplete(PathBuf),
+    FfmpegFailed(String),
     FfmpegFound(bool),
 }
 
@@ -90,24 +156,185 @@ impl Application for VideoProcessor {
                 if !file_path.is_file() {
                     return Command::none();
                 }
-                *self = VideoProcessor::SelectTarget(SelectTargetCtx { video: file_path });
+
+                match self {
+                    VideoProcessor::SelectFile => {
+                        let items = vec![QueueItem::pending(file_path.clone())];
+                        *self = VideoProcessor::Probing(items);
+                        Command::perform(ffprobe_media(file_path), Message::Probed)
+                    }
+                    VideoProcessor::Probing(items) => {
+                        items.push(QueueItem::pending(file_path));
+                        Command::none()
+                    }
+                    VideoProcessor::SelectTarget(ctx) => {
+                        if is_subtitle_file(&file_path) {
+                            // GIF output never muxes or burns in a subtitle
+                            // track (see build_transcode_command), and the
+                            // subtitle controls are hidden for it in the
+                            // view, so don't let a drop set it behind the
+                            // user's back either.
+                            if ctx.options.container != Container::Gif {
+                                ctx.options.subtitle_path = Some(file_path);
+                            }
+                        } else {
+                            ctx.items.push(QueueItem::pending(file_path));
+                        }
+                        Command::none()
+                    }
+                    VideoProcessor::SelectTransition(ctx) => {
+                        if ctx.options.intro_path.is_none() {
+                            ctx.options.intro_path = Some(file_path);
+                        } else if ctx.options.outro_path.is_none() {
+                            ctx.options.outro_path = Some(file_path);
+                        }
+                        Command::none()
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::Probed(details) => {
+                let items = if let VideoProcessor::Probing(items) = self {
+                    items.clone()
+                } else {
+                    panic!("Wrong application state.")
+                };
+
+                *self = VideoProcessor::SelectTarget(SelectTargetCtx {
+                    items,
+                    media: details,
+                    ..Default::default()
+                });
                 Command::none()
             }
-            Message::Submit(video_type) => {
+            Message::Submit => {
                 let cur_ctx = if let VideoProcessor::SelectTarget(ctx) = self {
                     ctx.clone()
                 } else {
                     panic!("Wrong application state.")
                 };
 
-                *self = VideoProcessor::GeneratingFile;
-                Command::perform(ffmpeg_execute(cur_ctx.video, video_type), |path: PathBuf| {
-                    Message::FfmpegComplete(path)
-                })
+                if let Err(reason) = validate(&cur_ctx.options) {
+                    if let VideoProcessor::SelectTarget(ctx) = self {
+                        ctx.validation_error = Some(reason);
+                    }
+                    return Command::none();
+                }
+
+                let mut items = cur_ctx.items;
+                items[0].status = ItemStatus::Running;
+
+                *self = VideoProcessor::GeneratingFile(GeneratingCtx {
+                    items,
+                    current_index: 0,
+                    options: cur_ctx.options,
+                    progress: 0.0,
+                    warnings: Vec::new(),
+                });
+                Command::none()
+            }
+            Message::ContainerChanged(container) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.container = container;
+                    ctx.validation_error = None;
+                }
+                Command::none()
+            }
+            Message::VideoCodecChanged(codec) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.video_codec = codec;
+                    ctx.options.quality = codec.default_quality();
+                    ctx.validation_error = None;
+                }
+                Command::none()
+            }
+            Message::AudioCodecChanged(codec) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.audio_codec = codec;
+                    ctx.validation_error = None;
+                }
+                Command::none()
+            }
+            Message::QualityChanged(quality) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    if let Ok(quality) = quality.trim().parse() {
+                        ctx.options.quality = quality;
+                    }
+                }
+                Command::none()
+            }
+            Message::GifFpsChanged(fps) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.gif_fps = fps;
+                }
+                Command::none()
+            }
+            Message::GifWidthChanged(width) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.gif_width = width;
+                }
+                Command::none()
+            }
+            Message::SubtitleModeChanged(mode) => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.subtitle_mode = mode;
+                }
+                Command::none()
+            }
+            Message::ClearSubtitle => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    ctx.options.subtitle_path = None;
+                }
+                Command::none()
+            }
+            Message::OpenTransitionEditor => {
+                if let VideoProcessor::SelectTarget(ctx) = self {
+                    *self = VideoProcessor::SelectTransition(ctx.clone());
+                }
+                Command::none()
+            }
+            Message::CloseTransitionEditor => {
+                if let VideoProcessor::SelectTransition(ctx) = self {
+                    *self = VideoProcessor::SelectTarget(ctx.clone());
+                }
+                Command::none()
+            }
+            Message::TransitionSecsChanged(secs) => {
+                if let VideoProcessor::SelectTransition(ctx) = self {
+                    ctx.options.transition_secs = secs;
+                }
+                Command::none()
+            }
+            Message::ClearIntro => {
+                if let VideoProcessor::SelectTransition(ctx) = self {
+                    ctx.options.intro_path = None;
+                }
+                Command::none()
+            }
+            Message::ClearOutro => {
+                if let VideoProcessor::SelectTransition(ctx) = self {
+                    ctx.options.outro_path = None;
+                }
+                Command::none()
+            }
+            Message::Progress(fraction) => {
+                if let VideoProcessor::GeneratingFile(ctx) = self {
+                    ctx.progress = fraction;
+                }
+                Command::none()
+            }
+            Message::GifLimitsWarning(text) => {
+                if let VideoProcessor::GeneratingFile(ctx) = self {
+                    ctx.warnings.push(text);
+                }
+                Command::none()
             }
             Message::FfmpegComplete(p) => {
-                *self = VideoProcessor::Complete(CompleteCtx { target_path: p });
-
+                advance_queue(self, ItemStatus::Done(p));
+                Command::none()
+            }
+            Message::FfmpegFailed(reason) => {
+                advance_queue(self, ItemStatus::Failed(reason));
                 Command::none()
             }
             Message::FfmpegFound(is_exist) if !is_exist => {
@@ -120,20 +347,50 @@ impl Application for VideoProcessor {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        subscription::events().map(Message::EventOccurred)
+        let events = subscription::events().map(Message::EventOccurred);
+
+        if let VideoProcessor::GeneratingFile(ctx) = self {
+            Subscription::batch(vec![events, ffmpeg_progress_subscription(ctx.clone())])
+        } else {
+            events
+        }
     }
 
     fn view(&self) -> Element<Message> {
         match self {
             VideoProcessor::SelectFile => select_file_view(),
+            VideoProcessor::Probing(_) => probing_view(),
             VideoProcessor::SelectTarget(ctx) => select_target_view(ctx),
-            VideoProcessor::GeneratingFile => gen_file_view(),
-            VideoProcessor::Complete(ctx) => complete_view(&ctx.target_path),
+            VideoProcessor::SelectTransition(ctx) => select_transition_view(ctx),
+            VideoProcessor::GeneratingFile(ctx) => gen_file_view(ctx),
+            VideoProcessor::Complete(ctx) => complete_view(ctx),
             VideoProcessor::Error => error_view(),
         }
     }
 }
 
+/// Marks the just-finished queue item and either starts the next one or,
+/// once every item has a terminal status, moves to the batch summary.
+fn advance_queue(state: &mut VideoProcessor, finished_status: ItemStatus) {
+    let ctx = if let VideoProcessor::GeneratingFile(ctx) = state {
+        ctx
+    } else {
+        panic!("Wrong application state.")
+    };
+
+    ctx.items[ctx.current_index].status = finished_status;
+    ctx.current_index += 1;
+
+    if ctx.current_index < ctx.items.len() {
+        ctx.items[ctx.current_index].status = ItemStatus::Running;
+        ctx.progress = 0.0;
+    } else {
+        let items = ctx.items.clone();
+        let warnings = ctx.warnings.clone();
+        *state = VideoProcessor::Complete(CompleteCtx { items, warnings });
+    }
+}
+
 fn select_file_view() -> Element<'static, Message> {
     let txt = text("将源文件拖拽至此")
         .width(100)
@@ -153,38 +410,199 @@ fn select_file_view() -> Element<'static, Message> {
         .into()
 }
 
+fn probing_view() -> Element<'static, Message> {
+    let txt = text("正在解析媒体信息...")
+        .width(100)
+        .width(Length::Fill)
+        .horizontal_alignment(alignment::Horizontal::Center);
+
+    let content = Column::new()
+        .align_items(Alignment::Center)
+        .spacing(20)
+        .push(txt);
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
 fn select_target_view(ctx: &SelectTargetCtx) -> Element<'static, Message> {
-    let txt = text(&format!(
-        "要将 {} 转为:",
-        ctx.video
-            .file_name()
-            .expect("bad file name?")
-            .to_string_lossy()
-    ))
-    .size(28);
+    let txt = text(&format!("要将 {} 个文件转为:", ctx.items.len())).size(28);
 
-    let button_mp4 = button(
-        text("MP4")
-            .width(Length::Fill)
-            .horizontal_alignment(alignment::Horizontal::Center),
-    )
-    .width(Length::Fixed(100.))
-    .on_press(Message::Submit("mp4".to_string()));
+    let queue_list = ctx.items.iter().fold(Column::new().spacing(2), |col, item| {
+        col.push(text(item.label()).size(14))
+    });
+
+    let media_txt = text(ctx.media.describe()).size(14);
+
+    let codec_pickers = Row::new()
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .push(pick_list(
+            &Container::ALL[..],
+            Some(ctx.options.container),
+            Message::ContainerChanged,
+        ))
+        .push(pick_list(
+            &VideoCodec::ALL[..],
+            Some(ctx.options.video_codec),
+            Message::VideoCodecChanged,
+        ))
+        .push(pick_list(
+            &AudioCodec::ALL[..],
+            Some(ctx.options.audio_codec),
+            Message::AudioCodecChanged,
+        ));
+
+    let quality_row = Row::new()
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .push(text("质量(CRF)"))
+        .push(
+            text_input("质量", &ctx.options.quality.to_string())
+                .width(Length::Fixed(60.))
+                .on_input(Message::QualityChanged),
+        );
+
+    let mut content = Column::new()
+        .align_items(Alignment::Center)
+        .spacing(5)
+        .push(txt)
+        .push(queue_list)
+        .push(media_txt)
+        .push(codec_pickers);
+
+    if ctx.options.container == Container::Gif {
+        let gif_options = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(text("GIF 帧率"))
+            .push(
+                text_input("帧率", &ctx.options.gif_fps)
+                    .width(Length::Fixed(60.))
+                    .on_input(Message::GifFpsChanged),
+            )
+            .push(text("GIF 宽度"))
+            .push(
+                text_input("宽度", &ctx.options.gif_width)
+                    .width(Length::Fixed(60.))
+                    .on_input(Message::GifWidthChanged),
+            );
+        content = content.push(gif_options);
+    } else {
+        content = content.push(quality_row);
+    }
 
-    let button_gif = button(
-        text("GIF")
+    // GIF output never burns in or muxes a subtitle track, and never
+    // includes an intro/outro (see build_transcode_command), so those
+    // controls are hidden rather than offered and then silently ignored.
+    if ctx.options.container == Container::Gif {
+        content = content.push(text("GIF 不支持字幕与片头/片尾").size(14));
+    } else {
+        let subtitle_row: Element<Message> = match &ctx.options.subtitle_path {
+            Some(path) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.to_string_lossy().into_owned());
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(text(format!("字幕: {name}")).size(14))
+                    .push(pick_list(
+                        &SubtitleMode::ALL[..],
+                        Some(ctx.options.subtitle_mode),
+                        Message::SubtitleModeChanged,
+                    ))
+                    .push(button(text("移除字幕")).on_press(Message::ClearSubtitle))
+                    .into()
+            }
+            None => text("将 .srt/.ass 字幕文件拖拽至此可附加字幕").size(14).into(),
+        };
+        content = content.push(subtitle_row);
+
+        let transition_summary = match (
+            ctx.options.intro_path.is_some(),
+            ctx.options.outro_path.is_some(),
+        ) {
+            (false, false) => "未设置片头/片尾".to_string(),
+            (true, false) => "已设置片头".to_string(),
+            (false, true) => "已设置片尾".to_string(),
+            (true, true) => "已设置片头和片尾".to_string(),
+        };
+        let transition_button = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(text(transition_summary).size(14))
+            .push(button(text("添加片头/片尾")).on_press(Message::OpenTransitionEditor));
+        content = content.push(transition_button);
+    }
+
+    let submit_button = button(
+        text("开始转换")
             .width(Length::Fill)
             .horizontal_alignment(alignment::Horizontal::Center),
     )
     .width(Length::Fixed(100.))
-    .on_press(Message::Submit("gif".to_string()));
+    .on_press(Message::Submit);
+    content = content.push(submit_button);
+
+    if let Some(reason) = &ctx.validation_error {
+        content = content.push(text(reason).size(14));
+    }
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+fn select_transition_view(ctx: &SelectTargetCtx) -> Element<'static, Message> {
+    let txt = text("拖拽片头/片尾视频至此 (先拖片头，再拖片尾)").size(16);
+
+    let clip_row = |label: &str, path: &Option<PathBuf>, on_clear: Message| -> Element<'static, Message> {
+        let name = path
+            .as_ref()
+            .map(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "未设置".to_string());
+
+        Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(text(format!("{label}: {name}")).size(14))
+            .push(button(text("清除")).on_press(on_clear))
+            .into()
+    };
+
+    let transition_row = Row::new()
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .push(text("过渡时长(秒)"))
+        .push(
+            text_input("1", &ctx.options.transition_secs)
+                .width(Length::Fixed(60.))
+                .on_input(Message::TransitionSecsChanged),
+        );
+
+    let done_button = button(text("完成")).on_press(Message::CloseTransitionEditor);
 
     let content = Column::new()
         .align_items(Alignment::Center)
-        .spacing(5)
+        .spacing(10)
         .push(txt)
-        .push(button_mp4)
-        .push(button_gif);
+        .push(clip_row("片头", &ctx.options.intro_path, Message::ClearIntro))
+        .push(clip_row("片尾", &ctx.options.outro_path, Message::ClearOutro))
+        .push(transition_row)
+        .push(done_button);
 
     container(content)
         .width(Length::Fill)
@@ -194,16 +612,32 @@ fn select_target_view(ctx: &SelectTargetCtx) -> Element<'static, Message> {
         .into()
 }
 
-fn gen_file_view() -> Element<'static, Message> {
-    let txt = text("转换中...")
-        .width(100)
-        .width(Length::Fill)
-        .horizontal_alignment(alignment::Horizontal::Center);
+fn gen_file_view(ctx: &GeneratingCtx) -> Element<'static, Message> {
+    let txt = text(format!(
+        "转换中... ({}/{})",
+        ctx.current_index + 1,
+        ctx.items.len()
+    ))
+    .width(100)
+    .width(Length::Fill)
+    .horizontal_alignment(alignment::Horizontal::Center);
 
-    let content = Column::new()
+    let bar = progress_bar(0.0..=1.0, ctx.progress).width(Length::Fixed(300.));
+
+    let queue_list = ctx.items.iter().fold(Column::new().spacing(2), |col, item| {
+        col.push(text(item.label()).size(14))
+    });
+
+    let mut content = Column::new()
         .align_items(Alignment::Center)
         .spacing(20)
-        .push(txt);
+        .push(txt)
+        .push(bar)
+        .push(queue_list);
+
+    if !ctx.warnings.is_empty() {
+        content = content.push(warnings_view(&ctx.warnings));
+    }
 
     container(content)
         .width(Length::Fill)
@@ -213,22 +647,25 @@ fn gen_file_view() -> Element<'static, Message> {
         .into()
 }
 
-fn complete_view(dst_path: &Path) -> Element<'static, Message> {
+fn complete_view(ctx: &CompleteCtx) -> Element<'static, Message> {
     let txt = text("转换完成")
         .width(100)
         .width(Length::Fill)
         .horizontal_alignment(alignment::Horizontal::Center);
 
-    let path_txt = text(dst_path.to_str().unwrap())
-        .width(100)
-        .width(Length::Fill)
-        .horizontal_alignment(alignment::Horizontal::Center);
+    let queue_list = ctx.items.iter().fold(Column::new().spacing(2), |col, item| {
+        col.push(text(item.label()).size(14))
+    });
 
-    let content = Column::new()
+    let mut content = Column::new()
         .align_items(Alignment::Center)
         .spacing(20)
         .push(txt)
-        .push(path_txt);
+        .push(queue_list);
+
+    if !ctx.warnings.is_empty() {
+        content = content.push(warnings_view(&ctx.warnings));
+    }
 
     container(content)
         .width(Length::Fill)
@@ -238,6 +675,18 @@ fn complete_view(dst_path: &Path) -> Element<'static, Message> {
         .into()
 }
 
+/// Renders accumulated `GifPlan::warning` clamp notices (fps/width/area cut
+/// down to fit the configured media limits) so they reach the user even
+/// without a terminal, instead of only going to stdout.
+fn warnings_view(warnings: &[String]) -> Element<'static, Message> {
+    warnings
+        .iter()
+        .fold(Column::new().spacing(2), |col, warning| {
+            col.push(text(warning.clone()).size(12))
+        })
+        .into()
+}
+
 fn error_view() -> Element<'static, Message> {
     let txt = text("未安装ffmpeg!")
         .size(28)
@@ -258,78 +707,351 @@ fn error_view() -> Element<'static, Message> {
         .into()
 }
 
-fn _ffmpeg_execute(src_video: PathBuf, video_type: String) -> PathBuf {
-    let src_path = src_video.to_str().unwrap();
-    let dst_path = {
-        let filename_without_suffix = src_video
-            .file_stem()
-            .map(|name| name.to_str().expect("bad file path"))
-            .unwrap_or("output");
-
-        let dir = PathBuf::new()
-            .join("./")
-            .join("test/dist");
-
-        println!("dir: {}", dir.to_str().unwrap());
-        if dir.is_file() {
-            panic!("already has file.")
+fn run_ffmpeg(command: &mut process::Command) {
+    println!("{:?}", command);
+    let result = command.output().expect("ffmpeg execute failed.");
+    let output = if result.status.success() {
+        result.stdout
+    } else {
+        result.stderr
+    };
+    println!("{}", String::from_utf8(output).unwrap());
+}
+
+/// The two palettegen/paletteuse filter graphs for the two-pass GIF workflow.
+fn gif_palette_filters(fps: u32, width: i32) -> (String, String) {
+    (
+        format!("fps={fps},scale={width}:-1:flags=lanczos,palettegen=stats_mode=diff"),
+        format!("fps={fps},scale={width}:-1:flags=lanczos [x]; [x][1:v] paletteuse=dither=sierra2_4a"),
+    )
+}
+
+/// iced's `FileDropped` event is window-wide, not per-widget, so a dropped
+/// subtitle sidecar is told apart from a video to queue by extension rather
+/// than by which on-screen drop zone it landed on.
+fn is_subtitle_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("srt") | Some("ass")
+    )
+}
+
+/// ffmpeg input order for the crossfade chain: intro (if any), the main
+/// clip, then outro (if any).
+fn transition_inputs(src_video: &Path, options: &TranscodeOptions) -> Vec<PathBuf> {
+    let mut inputs = Vec::new();
+    if let Some(intro) = &options.intro_path {
+        inputs.push(intro.clone());
+    }
+    inputs.push(src_video.to_path_buf());
+    if let Some(outro) = &options.outro_path {
+        inputs.push(outro.clone());
+    }
+    inputs
+}
+
+/// Progress-bar denominator: the main clip's duration, or — when an
+/// intro/outro is attached and actually applied (GIF targets never mux
+/// them in, see `build_transcode_command`) — the overlapped duration of
+/// the whole crossfaded timeline. Without this the bar raced to 100% as
+/// soon as the main clip's `out_time_us` caught up, well before ffmpeg
+/// finished encoding the attached intro/outro.
+fn total_timeline_secs(src_video: &Path, options: &TranscodeOptions) -> f64 {
+    let inputs = transition_inputs(src_video, options);
+    if options.container != Container::Gif && inputs.len() > 1 {
+        let durations: Vec<f64> = inputs
+            .iter()
+            .map(|input| probe_media_sync(input).duration_secs)
+            .collect();
+        let transition_secs: f64 = options.transition_secs.trim().parse().unwrap_or(1.0);
+        transition_total_secs(&durations, transition_secs)
+    } else {
+        probe_media_sync(src_video).duration_secs
+    }
+}
+
+fn compute_dst_path(src_video: &Path, container: Container) -> PathBuf {
+    let filename_without_suffix = src_video
+        .file_stem()
+        .map(|name| name.to_str().expect("bad file path"))
+        .unwrap_or("output");
+
+    let dir = PathBuf::new().join("./").join("test/dist");
+
+    println!("dir: {}", dir.to_str().unwrap());
+    if dir.is_file() {
+        panic!("already has file.")
+    }
+    if !dir.is_dir() {
+        fs::create_dir(dir.clone()).expect("create dst dir failed.");
+    }
+    dir.join(filename_without_suffix.to_owned() + "." + container.extension())
+}
+
+/// Builds the non-GIF `-i .. -filter_complex/-vf .. -c:v .. -c:a ..` argument
+/// list shared by the runtime (progress-tracked) path. Kept separate from
+/// the GIF branch and from command construction so there is exactly one
+/// place that knows how codecs, transitions and subtitle muxing combine.
+fn transcode_args(src_video: &Path, options: &TranscodeOptions) -> Vec<String> {
+    let inputs = transition_inputs(src_video, options);
+    let subtitle_input_index = inputs.len();
+
+    let mut args: Vec<String> = Vec::new();
+    for input in &inputs {
+        args.push("-i".into());
+        args.push(input.to_string_lossy().into_owned());
+    }
+    if options.wants_subtitle_mux() {
+        args.push("-i".into());
+        args.push(
+            options
+                .subtitle_path
+                .as_ref()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+        );
+    }
+
+    if inputs.len() > 1 {
+        let durations: Vec<f64> = inputs
+            .iter()
+            .map(|input| probe_media_sync(input).duration_secs)
+            .collect();
+        let transition_secs: f64 = options.transition_secs.trim().parse().unwrap_or(1.0);
+        let transition = build_transition_filter(&durations, transition_secs);
+
+        let mut filter_complex = transition.filter_complex;
+        let mut video_label = transition.video_label;
+        if let Some(extra) = options.video_filter() {
+            filter_complex.push_str(&format!(";[{video_label}]{extra}[vout]"));
+            video_label = "vout".to_string();
+        }
+
+        args.push("-filter_complex".into());
+        args.push(filter_complex);
+        args.push("-map".into());
+        args.push(format!("[{video_label}]"));
+        args.push("-map".into());
+        args.push(format!("[{}]", transition.audio_label));
+        if options.wants_subtitle_mux() {
+            args.push("-map".into());
+            args.push(format!("{subtitle_input_index}:s"));
         }
-        if !dir.is_dir() {
-            fs::create_dir(dir.clone()).expect("create dst dir failed.");
+    } else {
+        if let Some(vf) = options.video_filter() {
+            args.push("-vf".into());
+            args.push(vf);
         }
-        if video_type == "mp4" {
-            dir.join(filename_without_suffix.to_owned() + ".mp4")
-        } else {
-            dir.join(filename_without_suffix.to_owned() + ".gif")
+        if options.wants_subtitle_mux() {
+            args.push("-map".into());
+            args.push("0".into());
+            args.push("-map".into());
+            args.push("1".into());
         }
-    };
+    }
+
+    args.extend(options.video_codec.ffmpeg_args(options.quality));
+    args.extend(options.audio_codec.ffmpeg_args());
+    if options.wants_subtitle_mux() {
+        args.push("-c:s".into());
+        args.push("mov_text".into());
+    }
+
+    args
+}
+
+/// Builds the ffmpeg invocation for the runtime (progress-tracked) path.
+/// For GIF targets the palette is still generated synchronously up front
+/// (it is quick); only the long-running second pass reports progress.
+/// Returns the command to spawn, the eventual output path, an optional
+/// palette file to clean up once the command finishes, and — for GIF
+/// targets whose requested fps/width got clamped — a warning to surface
+/// in the UI.
+fn build_transcode_command(
+    src_video: &Path,
+    options: &TranscodeOptions,
+) -> (TokioCommand, PathBuf, Option<PathBuf>, Option<String>) {
+    let src_path = src_video.to_str().unwrap().to_string();
+    let dst_path = compute_dst_path(src_video, options.container);
 
     if dst_path.is_file() {
-        fs::remove_file(dst_path.clone()).expect("remove file failed.")
+        fs::remove_file(&dst_path).expect("remove file failed.");
     }
 
-    let mut command = process::Command::new("ffmpeg");
-    if video_type == "mp4" {
+    if options.container == Container::Gif {
+        let requested_fps: u32 = options.gif_fps.trim().parse().unwrap_or(15);
+        let requested_width: u32 = options.gif_width.trim().parse().unwrap_or(480);
+        let plan =
+            load_media_limits().plan_gif(&probe_media_sync(src_video), requested_fps, requested_width);
+        if let Some(warning) = &plan.warning {
+            println!("gif limits: {warning}");
+        }
+        let (fps, width) = (plan.fps, plan.width as i32);
+        let palette_path =
+            env::temp_dir().join(format!("video-convert-palette-{}.png", process::id()));
+        let (palette_filter, paletteuse_filter) = gif_palette_filters(fps, width);
+
+        run_ffmpeg(
+            process::Command::new("ffmpeg")
+                .arg("-i")
+                .arg(&src_path)
+                .arg("-vf")
+                .arg(&palette_filter)
+                .arg("-y")
+                .arg(&palette_path),
+        );
+
+        let mut command = TokioCommand::new("ffmpeg");
         command
-        .arg("-i")
-        .arg(src_path)
-        .arg("-vf")
-        .arg("scale=trunc(iw/2)*2:trunc(ih/2)*2")
-        .arg(dst_path.clone());
+            .arg("-i")
+            .arg(&src_path)
+            .arg("-i")
+            .arg(&palette_path)
+            .arg("-lavfi")
+            .arg(&paletteuse_filter)
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-nostats")
+            .arg("-y")
+            .arg(&dst_path);
+        (command, dst_path, Some(palette_path), plan.warning)
     } else {
+        let mut command = TokioCommand::new("ffmpeg");
         command
-        .arg("-i")
-        .arg(src_path)
-        .arg(dst_path.clone());
+            .args(transcode_args(src_video, options))
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-nostats")
+            .arg("-y")
+            .arg(&dst_path);
+        (command, dst_path, None, None)
     }
+}
 
-    println!("{:?}", command);
-    let result = command.output().expect("ffmpeg execute failed.");
-    let output = if result.status.success() {
-        result.stdout
+#[derive(Debug)]
+enum ProgressState {
+    Starting {
+        src_video: PathBuf,
+        options: TranscodeOptions,
+    },
+    Reading {
+        child: Child,
+        lines: tokio::io::Lines<BufReader<tokio::process::ChildStdout>>,
+        total_secs: f64,
+        dst_path: PathBuf,
+        palette_path: Option<PathBuf>,
+    },
+    Finished,
+}
+
+fn finish(
+    child: &mut Child,
+    dst_path: PathBuf,
+    palette_path: Option<PathBuf>,
+) -> (Message, ProgressState) {
+    if let Some(palette) = &palette_path {
+        let _ = fs::remove_file(palette);
+    }
+    let succeeded = child
+        .try_wait()
+        .ok()
+        .flatten()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    let message = if succeeded {
+        Message::FfmpegComplete(dst_path)
     } else {
-        result.stderr
+        Message::FfmpegFailed("ffmpeg exited with an error, see logs.".to_string())
     };
-
-    println!("{}", String::from_utf8(output).unwrap());
-    dst_path
+    (message, ProgressState::Finished)
 }
 
-#[test]
-fn test_ffmpeg_execute() {
-    _ffmpeg_execute(
-        PathBuf::new().join("/mnt/yjn/DATA/Videos/录屏/录屏 2023年04月17日 19时13分35秒.webm"),
-        "mp4".to_string()
-    );
-}
+/// Drives a single queue item's ffmpeg conversion to completion, translating
+/// its `-progress pipe:1` output into `Message::Progress` fractions of the
+/// item's output timeline (`total_timeline_secs`, probed fresh for each
+/// item) and finishing with
+/// `Message::FfmpegComplete`/`Message::FfmpegFailed`.
+fn ffmpeg_progress_subscription(ctx: GeneratingCtx) -> Subscription<Message> {
+    let src_video = ctx.items[ctx.current_index].path.clone();
+    subscription::unfold(
+        src_video.clone(),
+        ProgressState::Starting {
+            src_video,
+            options: ctx.options,
+        },
+        |state| async move {
+            match state {
+                ProgressState::Starting { src_video, options } => {
+                    let total_secs = total_timeline_secs(&src_video, &options);
+                    let (mut command, dst_path, palette_path, warning) =
+                        build_transcode_command(&src_video, &options);
+                    let mut child = command
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .expect("ffmpeg spawn failed.");
+                    let stdout = child.stdout.take().expect("ffmpeg stdout not piped.");
+                    let lines = BufReader::new(stdout).lines();
 
-async fn ffmpeg_execute(src_video: PathBuf, video_type: String) -> PathBuf {
-    if video_type == "mp4" {
-        _ffmpeg_execute(src_video, video_type)
-    } else {
-        _ffmpeg_execute(src_video, video_type)
-    }
-    
+                    let message = match warning {
+                        Some(text) => Message::GifLimitsWarning(text),
+                        None => Message::Progress(0.0),
+                    };
+                    (
+                        message,
+                        ProgressState::Reading {
+                            child,
+                            lines,
+                            total_secs,
+                            dst_path,
+                            palette_path,
+                        },
+                    )
+                }
+                ProgressState::Reading {
+                    mut child,
+                    mut lines,
+                    total_secs,
+                    dst_path,
+                    palette_path,
+                } => loop {
+                    let line = match lines.next_line().await {
+                        Ok(Some(line)) => line,
+                        _ => {
+                            let _ = child.wait().await;
+                            break finish(&mut child, dst_path, palette_path);
+                        }
+                    };
+
+                    if let Some(raw) = line.strip_prefix("out_time_us=") {
+                        let micros: f64 = raw.trim().parse().unwrap_or(0.0);
+                        let fraction = if total_secs > 0.0 {
+                            ((micros / 1_000_000.0) / total_secs).clamp(0.0, 1.0)
+                        } else {
+                            0.0
+                        };
+                        break (
+                            Message::Progress(fraction as f32),
+                            ProgressState::Reading {
+                                child,
+                                lines,
+                                total_secs,
+                                dst_path,
+                                palette_path,
+                            },
+                        );
+                    }
+
+                    if line.trim() == "progress=end" {
+                        let _ = child.wait().await;
+                        break finish(&mut child, dst_path, palette_path);
+                    }
+                },
+                ProgressState::Finished => std::future::pending().await,
+            }
+        },
+    )
 }
 
 fn _ffmpeg_found() -> bool {
@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+use std::process;
+
+/// Stream/container facts pulled from `ffprobe`, used for the source
+/// summary in the target view, the progress percentage and the GIF media
+/// limit checks instead of flying blind into ffmpeg.
+#[derive(Debug, Default, Clone)]
+pub struct MediaDetails {
+    pub width: u32,
+    pub height: u32,
+    pub codec: String,
+    pub frame_rate: f64,
+    pub duration_secs: f64,
+}
+
+impl MediaDetails {
+    /// Human readable summary, e.g. "h264, 1920×1080, 30fps, 00:02:13".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, {}×{}, {}fps, {}",
+            self.codec,
+            self.width,
+            self.height,
+            self.frame_rate.round(),
+            format_duration(self.duration_secs)
+        )
+    }
+}
+
+fn format_duration(secs: f64) -> String {
+    let total = secs.round() as u64;
+    let h = total / 3600;
+    let m = (total % 3600) / 60;
+    let s = total % 60;
+    format!("{:02}:{:02}:{:02}", h, m, s)
+}
+
+fn parse_frame_rate(raw: &str) -> f64 {
+    if let Some((num, den)) = raw.split_once('/') {
+        let num: f64 = num.parse().unwrap_or(0.0);
+        let den: f64 = den.parse().unwrap_or(1.0);
+        if den != 0.0 {
+            return num / den;
+        }
+    }
+    raw.parse().unwrap_or(0.0)
+}
+
+pub fn probe_media_sync(src_video: &std::path::Path) -> MediaDetails {
+    _ffprobe_media(src_video.to_path_buf())
+}
+
+fn _ffprobe_media(src_video: PathBuf) -> MediaDetails {
+    let src_path = src_video.to_str().expect("bad file path");
+
+    let output = process::Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("v:0")
+        .arg("-show_entries")
+        .arg("stream=width,height,codec_name,r_frame_rate")
+        .arg("-show_entries")
+        .arg("format=duration")
+        .arg("-of")
+        .arg("default=noprint_wrappers=1:nokey=1")
+        .arg(src_path)
+        .output()
+        .expect("ffprobe execute failed.");
+
+    let stdout = String::from_utf8(output.stdout).expect("ffprobe produced non-utf8 output");
+    let mut lines = stdout.lines();
+
+    let width = lines.next().unwrap_or("0").trim().parse().unwrap_or(0);
+    let height = lines.next().unwrap_or("0").trim().parse().unwrap_or(0);
+    let codec = lines.next().unwrap_or("unknown").trim().to_string();
+    let frame_rate = parse_frame_rate(lines.next().unwrap_or("0/1").trim());
+    let duration_secs = lines.next().unwrap_or("0").trim().parse().unwrap_or(0.0);
+
+    MediaDetails {
+        width,
+        height,
+        codec,
+        frame_rate,
+        duration_secs,
+    }
+}
+
+pub async fn ffprobe_media(src_video: PathBuf) -> MediaDetails {
+    _ffprobe_media(src_video)
+}
+
+#[test]
+fn test_parse_frame_rate_fraction() {
+    assert_eq!(parse_frame_rate("30000/1001"), 30000.0 / 1001.0);
+    assert_eq!(parse_frame_rate("25/1"), 25.0);
+}
+
+#[test]
+fn test_parse_frame_rate_plain_number() {
+    assert_eq!(parse_frame_rate("24"), 24.0);
+}
+
+#[test]
+fn test_parse_frame_rate_garbage_defaults_to_zero() {
+    assert_eq!(parse_frame_rate("not-a-rate"), 0.0);
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(3723.0), "01:02:03");
+    assert_eq!(format_duration(0.0), "00:00:00");
+}
+
+#[test]
+fn test_describe_includes_frame_rate() {
+    let media = MediaDetails {
+        width: 1920,
+        height: 1080,
+        codec: "h264".to_string(),
+        frame_rate: 29.97,
+        duration_secs: 133.0,
+    };
+    assert_eq!(media.describe(), "h264, 1920×1080, 30fps, 00:02:13");
+}
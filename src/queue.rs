@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+/// Where a single dropped file is in the batch pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemStatus {
+    Pending,
+    Running,
+    Done(PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueItem {
+    pub path: PathBuf,
+    pub status: ItemStatus,
+}
+
+impl QueueItem {
+    pub fn pending(path: PathBuf) -> Self {
+        QueueItem {
+            path,
+            status: ItemStatus::Pending,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.to_string_lossy().into_owned());
+
+        match &self.status {
+            ItemStatus::Pending => format!("{name} - 等待中"),
+            ItemStatus::Running => format!("{name} - 转换中"),
+            ItemStatus::Done(dst) => format!("{name} -> {}", dst.to_string_lossy()),
+            ItemStatus::Failed(reason) => format!("{name} - 失败: {reason}"),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Video codecs offered in the target picker. AV1 uses `libsvtav1` rather
+/// than `libaom-av1`: it is dramatically faster at a comparable quality,
+/// which matters for a desktop GUI tool people actually wait in front of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 4] = [
+        VideoCodec::H264,
+        VideoCodec::H265,
+        VideoCodec::Vp9,
+        VideoCodec::Av1,
+    ];
+
+    /// A sane default CRF for this codec. The CRF scale is not comparable
+    /// across codecs, so each one gets its own default rather than sharing
+    /// a single "quality" number.
+    pub fn default_quality(&self) -> u32 {
+        match self {
+            VideoCodec::H264 => 23,
+            VideoCodec::H265 => 28,
+            VideoCodec::Vp9 => 31,
+            VideoCodec::Av1 => 28,
+        }
+    }
+
+    pub fn ffmpeg_args(&self, quality: u32) -> Vec<String> {
+        match self {
+            VideoCodec::H264 => vec![
+                "-c:v".into(),
+                "libx264".into(),
+                "-crf".into(),
+                quality.to_string(),
+            ],
+            VideoCodec::H265 => vec![
+                "-c:v".into(),
+                "libx265".into(),
+                "-crf".into(),
+                quality.to_string(),
+            ],
+            VideoCodec::Vp9 => vec![
+                "-c:v".into(),
+                "libvpx-vp9".into(),
+                "-crf".into(),
+                quality.to_string(),
+                "-b:v".into(),
+                "0".into(),
+            ],
+            VideoCodec::Av1 => vec![
+                "-c:v".into(),
+                "libsvtav1".into(),
+                "-preset".into(),
+                "7".into(),
+                "-crf".into(),
+                quality.to_string(),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::H265 => "H.265",
+            VideoCodec::Vp9 => "VP9",
+            VideoCodec::Av1 => "AV1",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Flac,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 3] = [AudioCodec::Aac, AudioCodec::Opus, AudioCodec::Flac];
+
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let codec_name = match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "libopus",
+            AudioCodec::Flac => "flac",
+        };
+        vec!["-c:a".into(), codec_name.into()]
+    }
+}
+
+impl fmt::Display for AudioCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Opus => "Opus",
+            AudioCodec::Flac => "FLAC",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Mp4,
+    WebM,
+    Gif,
+}
+
+impl Container {
+    pub const ALL: [Container; 3] = [Container::Mp4, Container::WebM, Container::Gif];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::WebM => "webm",
+            Container::Gif => "gif",
+        }
+    }
+}
+
+impl fmt::Display for Container {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Container::Mp4 => "MP4",
+            Container::WebM => "WebM",
+            Container::Gif => "GIF",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How an attached subtitle sidecar is applied. Burn-in re-encodes it into
+/// the picture (works everywhere, cannot be turned off by the viewer); soft
+/// mux adds it as a selectable text track (toggleable, but only MP4/MOV
+/// players handle `mov_text` reliably).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleMode {
+    Burn,
+    Soft,
+}
+
+impl SubtitleMode {
+    pub const ALL: [SubtitleMode; 2] = [SubtitleMode::Burn, SubtitleMode::Soft];
+}
+
+impl fmt::Display for SubtitleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SubtitleMode::Burn => "硬字幕(烧录)",
+            SubtitleMode::Soft => "软字幕(可关闭)",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// ffmpeg's filtergraph syntax treats `:` and `\` as separators, so a path
+/// used inside a `subtitles=...` filter (Windows paths, or any path with a
+/// colon) has to be escaped before it can be embedded in the filter string.
+fn escape_subtitle_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace(':', "\\:")
+}
+
+/// The structured replacement for the old two-string `Submit` path: enough
+/// to build a full ffmpeg argument list for any container/codec pairing
+/// this app offers.
+#[derive(Debug, Clone)]
+pub struct TranscodeOptions {
+    pub container: Container,
+    pub video_codec: VideoCodec,
+    pub audio_codec: AudioCodec,
+    pub quality: u32,
+    pub gif_fps: String,
+    pub gif_width: String,
+    pub subtitle_path: Option<PathBuf>,
+    pub subtitle_mode: SubtitleMode,
+    pub intro_path: Option<PathBuf>,
+    pub outro_path: Option<PathBuf>,
+    pub transition_secs: String,
+}
+
+impl Default for TranscodeOptions {
+    fn default() -> Self {
+        let video_codec = VideoCodec::H264;
+        TranscodeOptions {
+            container: Container::Mp4,
+            quality: video_codec.default_quality(),
+            video_codec,
+            audio_codec: AudioCodec::Aac,
+            gif_fps: "15".to_string(),
+            gif_width: "480".to_string(),
+            subtitle_path: None,
+            subtitle_mode: SubtitleMode::Burn,
+            intro_path: None,
+            outro_path: None,
+            transition_secs: "1".to_string(),
+        }
+    }
+}
+
+impl TranscodeOptions {
+    /// The `-vf` chain for non-GIF containers: MP4 needs even dimensions
+    /// for yuv420p encoders, and a burn-in subtitle filter is appended when
+    /// requested. Returns `None` when neither applies, so callers can skip
+    /// `-vf` entirely rather than pass an empty filter.
+    pub fn video_filter(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.container == Container::Mp4 {
+            parts.push("scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string());
+        }
+        if self.subtitle_mode == SubtitleMode::Burn {
+            if let Some(path) = &self.subtitle_path {
+                parts.push(format!("subtitles={}", escape_subtitle_path(path)));
+            }
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(","))
+        }
+    }
+
+    /// Whether a second `-i <subtitle>` input plus `-c:s mov_text -map 0
+    /// -map 1` needs to be added to mux the subtitle track in untouched.
+    pub fn wants_subtitle_mux(&self) -> bool {
+        self.subtitle_mode == SubtitleMode::Soft && self.subtitle_path.is_some()
+    }
+}
+
+/// The `-filter_complex` graph and the final `[label]`s to `-map`, joining
+/// an optional intro/outro onto the main clip with crossfades.
+pub struct TransitionFilter {
+    pub filter_complex: String,
+    pub video_label: String,
+    pub audio_label: String,
+}
+
+/// Total length of the crossfade-joined timeline, in the same overlapped
+/// (not summed) accounting `build_transition_filter` uses for its offsets:
+/// each later segment only adds `duration - transition_secs`, since the
+/// crossfade plays the two clips on top of each other for `transition_secs`.
+pub fn transition_total_secs(durations: &[f64], transition_secs: f64) -> f64 {
+    let mut elapsed = durations[0];
+    for duration in durations.iter().skip(1) {
+        elapsed = elapsed - transition_secs + duration;
+    }
+    elapsed
+}
+
+/// Chains `xfade`/`acrossfade` across `durations` (one entry per ffmpeg
+/// input, in the same order the inputs are passed on the command line).
+/// Each crossfade starts `transition_secs` before its predecessor ends, per
+/// ffmpeg's `offset = d1 - t` rule, and the running `elapsed` is kept in
+/// the overlapped (not summed) timeline so a third segment lines up after
+/// two crossfades instead of just one.
+pub fn build_transition_filter(durations: &[f64], transition_secs: f64) -> TransitionFilter {
+    let mut filters = Vec::new();
+    let mut video_label = "0:v".to_string();
+    let mut audio_label = "0:a".to_string();
+    let mut elapsed = durations[0];
+
+    for (i, duration) in durations.iter().enumerate().skip(1) {
+        let offset = elapsed - transition_secs;
+        let next_video = format!("v{i}");
+        let next_audio = format!("a{i}");
+        filters.push(format!(
+            "[{video_label}][{i}:v]xfade=transition=fadeblack:duration={transition_secs}:offset={offset:.3}[{next_video}]"
+        ));
+        filters.push(format!(
+            "[{audio_label}][{i}:a]acrossfade=d={transition_secs}[{next_audio}]"
+        ));
+        video_label = next_video;
+        audio_label = next_audio;
+        elapsed = elapsed - transition_secs + duration;
+    }
+
+    TransitionFilter {
+        filter_complex: filters.join(";"),
+        video_label,
+        audio_label,
+    }
+}
+
+/// Rejects codec/container pairings ffmpeg either can't mux or that nobody
+/// actually wants (e.g. H.264 cannot go in WebM). AV1-in-MP4 is always
+/// rejected; there is no option to allow it.
+pub fn validate(options: &TranscodeOptions) -> Result<(), String> {
+    if options.container == Container::Gif {
+        return Ok(());
+    }
+
+    if options.wants_subtitle_mux() && options.container != Container::Mp4 {
+        return Err("Soft-mux subtitles require an MP4 container (mov_text is MP4-only).".to_string());
+    }
+
+    match (options.container, options.video_codec) {
+        (Container::Mp4, VideoCodec::Vp9) => {
+            return Err("VP9 requires a WebM container.".to_string())
+        }
+        (Container::Mp4, VideoCodec::Av1) => {
+            return Err("AV1-in-MP4 is not supported; choose WebM.".to_string())
+        }
+        (Container::WebM, VideoCodec::H264) | (Container::WebM, VideoCodec::H265) => {
+            return Err("H.264/H.265 require an MP4 container.".to_string())
+        }
+        _ => {}
+    }
+
+    match (options.container, options.audio_codec) {
+        (Container::Mp4, AudioCodec::Opus) | (Container::Mp4, AudioCodec::Flac) => {
+            return Err("MP4 container requires AAC audio.".to_string())
+        }
+        (Container::WebM, AudioCodec::Aac) | (Container::WebM, AudioCodec::Flac) => {
+            return Err("WebM container requires Opus audio.".to_string())
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_transition_total_secs_overlaps_not_sums() {
+    // Three 10s clips crossfaded by 2s each: the second and third segments
+    // only contribute their non-overlapped tail to the total.
+    let total = transition_total_secs(&[10.0, 10.0, 10.0], 2.0);
+    assert_eq!(total, 26.0);
+}
+
+#[test]
+fn test_transition_total_secs_no_transition_clips() {
+    assert_eq!(transition_total_secs(&[42.0], 1.0), 42.0);
+}
+
+#[test]
+fn test_build_transition_filter_single_crossfade() {
+    let transition = build_transition_filter(&[10.0, 5.0], 2.0);
+
+    assert_eq!(transition.video_label, "v1");
+    assert_eq!(transition.audio_label, "a1");
+    assert_eq!(
+        transition.filter_complex,
+        "[0:v][1:v]xfade=transition=fadeblack:duration=2:offset=8.000[v1];\
+[0:a][1:a]acrossfade=d=2[a1]"
+    );
+}
+
+#[test]
+fn test_build_transition_filter_chains_offsets_on_overlapped_timeline() {
+    // The third segment's offset must be measured from the overlapped
+    // (not summed) elapsed time, or it starts transition_secs too late.
+    let transition = build_transition_filter(&[10.0, 10.0, 10.0], 2.0);
+
+    assert!(transition.filter_complex.contains("offset=8.000"));
+    assert!(transition.filter_complex.contains("offset=16.000"));
+    assert_eq!(transition.video_label, "v2");
+    assert_eq!(transition.audio_label, "a2");
+}
+
+#[test]
+fn test_validate_gif_always_ok() {
+    let mut options = TranscodeOptions::default();
+    options.container = Container::Gif;
+    options.subtitle_path = Some(PathBuf::from("a.srt"));
+    options.subtitle_mode = SubtitleMode::Soft;
+    assert!(validate(&options).is_ok());
+}
+
+#[test]
+fn test_validate_rejects_soft_mux_outside_mp4() {
+    let mut options = TranscodeOptions::default();
+    options.container = Container::WebM;
+    options.video_codec = VideoCodec::Vp9;
+    options.audio_codec = AudioCodec::Opus;
+    options.subtitle_path = Some(PathBuf::from("a.srt"));
+    options.subtitle_mode = SubtitleMode::Soft;
+    assert!(validate(&options).is_err());
+}
+
+#[test]
+fn test_validate_rejects_webm_flac() {
+    let mut options = TranscodeOptions::default();
+    options.container = Container::WebM;
+    options.video_codec = VideoCodec::Vp9;
+    options.audio_codec = AudioCodec::Flac;
+    assert!(validate(&options).is_err());
+}
+
+#[test]
+fn test_validate_accepts_webm_vp9_opus() {
+    let mut options = TranscodeOptions::default();
+    options.container = Container::WebM;
+    options.video_codec = VideoCodec::Vp9;
+    options.audio_codec = AudioCodec::Opus;
+    assert!(validate(&options).is_ok());
+}